@@ -0,0 +1,104 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use reqwest::{header, Client, Url};
+
+use crate::error::AppError;
+
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    etag: String,
+    body: String,
+}
+
+/// One instance backs both the repository metadata fetch and the languages
+/// fetch; callers pass their own key prefix (e.g. `"repo"` vs `"languages"`)
+/// so the two don't collide in the same map.
+#[derive(Debug, Default)]
+pub struct EtagCache {
+    entries: Mutex<HashMap<String, CachedResponse>>,
+}
+
+impl EtagCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn fetch(
+        &self,
+        client: &Client,
+        key: &str,
+        url: Url,
+        github_token: Option<&str>,
+    ) -> Result<String, AppError> {
+        let cached = self.entries.lock().unwrap().get(key).cloned();
+
+        let mut request = client.get(url).header("User-Agent", "repos-toolbox-api");
+        if let Some(token) = github_token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(cached) = &cached {
+            request = request.header(header::IF_NONE_MATCH, cached.etag.clone());
+        }
+
+        metrics::counter!("github_upstream_requests_total").increment(1);
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::Upstream(reqwest::StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        record_rate_limit_remaining(&response);
+
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            metrics::counter!("github_cache_hits_total").increment(1);
+            if let Some(cached) = cached {
+                return Ok(cached.body);
+            }
+            return Err(AppError::Upstream(
+                reqwest::StatusCode::BAD_GATEWAY,
+                "received 304 Not Modified with no cached body".to_string(),
+            ));
+        }
+
+        metrics::counter!("github_cache_misses_total").increment(1);
+
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(AppError::Upstream(status, status.to_string()));
+        }
+
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| AppError::Upstream(reqwest::StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        if let Some(etag) = etag {
+            self.entries.lock().unwrap().insert(
+                key.to_string(),
+                CachedResponse {
+                    etag,
+                    body: body.clone(),
+                },
+            );
+        }
+
+        Ok(body)
+    }
+}
+
+fn record_rate_limit_remaining(response: &reqwest::Response) {
+    if let Some(remaining) = response
+        .headers()
+        .get("x-ratelimit-remaining")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<f64>().ok())
+    {
+        metrics::gauge!("github_rate_limit_remaining").set(remaining);
+    }
+}
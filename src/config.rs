@@ -0,0 +1,25 @@
+use octocrab::Octocrab;
+use shuttle_runtime::SecretStore;
+
+pub struct AppConfig {
+    pub github_token: Option<String>,
+}
+
+impl AppConfig {
+    pub fn from_secrets(secrets: &SecretStore) -> Self {
+        // Fall back to the process environment so `cargo run` works
+        // locally without Shuttle's secret store.
+        let github_token = secrets
+            .get("GITHUB_TOKEN")
+            .or_else(|| std::env::var("GITHUB_TOKEN").ok());
+
+        Self { github_token }
+    }
+}
+
+pub fn build_octocrab(config: &AppConfig) -> octocrab::Result<Octocrab> {
+    match &config.github_token {
+        Some(token) => Octocrab::builder().personal_token(token.clone()).build(),
+        None => Octocrab::builder().build(),
+    }
+}
@@ -0,0 +1,136 @@
+use askama::Template;
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::ErrorTemplate;
+
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("repository not found")]
+    RepoNotFound,
+
+    #[error("upstream GitHub error ({0}): {1}")]
+    Upstream(StatusCode, String),
+
+    #[error("failed to parse GitHub response: {0}")]
+    Deserialize(#[from] serde_json::Error),
+
+    #[error("failed to render template: {0}")]
+    Render(#[from] askama::Error),
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorBody {
+    code: u16,
+    message: String,
+}
+
+fn wants_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(|accept| accept.contains("text/html"))
+        .unwrap_or(false)
+}
+
+impl AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::RepoNotFound => StatusCode::NOT_FOUND,
+            AppError::Upstream(_, _) => StatusCode::BAD_GATEWAY,
+            AppError::Deserialize(_) => StatusCode::BAD_GATEWAY,
+            AppError::Render(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    // There's no blanket `impl IntoResponse for AppError`: picking HTML vs.
+    // JSON needs the request's `Accept` header, which the trait doesn't
+    // give us access to. Callers go through this method instead.
+    pub fn into_response_with_headers(self, headers: &HeaderMap) -> Response {
+        let code = self.status_code();
+        let message = self.to_string();
+
+        if wants_html(headers) {
+            let template = ErrorTemplate {
+                code,
+                message: message.clone(),
+            };
+            return match template.render() {
+                Ok(html) => (code, Html(html)).into_response(),
+                Err(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "failed to render error template",
+                )
+                    .into_response(),
+            };
+        }
+
+        (
+            code,
+            Json(ErrorBody {
+                code: code.as_u16(),
+                message,
+            }),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers_with_accept(accept: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, accept.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn wants_html_matches_text_html_accept_header() {
+        assert!(wants_html(&headers_with_accept("text/html")));
+        assert!(wants_html(&headers_with_accept(
+            "text/html,application/xhtml+xml"
+        )));
+    }
+
+    #[test]
+    fn wants_html_is_false_without_a_matching_accept_header() {
+        assert!(!wants_html(&headers_with_accept("application/json")));
+        assert!(!wants_html(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn status_code_maps_each_variant() {
+        assert_eq!(AppError::RepoNotFound.status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            AppError::Upstream(StatusCode::IM_A_TEAPOT, "oops".into()).status_code(),
+            StatusCode::BAD_GATEWAY
+        );
+    }
+
+    #[test]
+    fn into_response_with_headers_returns_json_by_default() {
+        let response =
+            AppError::RepoNotFound.into_response_with_headers(&HeaderMap::new());
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+    }
+
+    #[test]
+    fn into_response_with_headers_returns_html_when_requested() {
+        let response = AppError::RepoNotFound
+            .into_response_with_headers(&headers_with_accept("text/html"));
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let content_type = response.headers().get(header::CONTENT_TYPE).unwrap();
+        assert!(content_type.to_str().unwrap().starts_with("text/html"));
+    }
+}
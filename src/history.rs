@@ -0,0 +1,195 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const HOUR: u64 = 60 * 60;
+pub const DAY: u64 = 24 * HOUR;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Snapshot {
+    pub stargazers_count: u32,
+    pub forks_count: u32,
+    pub watchers_count: u32,
+}
+
+// All zero when there isn't enough history yet to compute a real delta.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Deltas {
+    pub stars: i64,
+    pub forks: i64,
+    pub watchers: i64,
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// A background poller (spawned in `axum()`) appends snapshots for every
+// repo that's been requested at least once; handlers only read deltas out
+// of whatever history has accumulated so far.
+#[derive(Default)]
+pub struct HistoryStore {
+    tracked: Mutex<HashSet<(String, String)>>,
+    snapshots: Mutex<HashMap<(String, String), VecDeque<(u64, Snapshot)>>>,
+}
+
+impl HistoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn track(&self, owner: &str, repo: &str) {
+        self.tracked
+            .lock()
+            .unwrap()
+            .insert((owner.to_string(), repo.to_string()));
+    }
+
+    pub fn tracked_repos(&self) -> Vec<(String, String)> {
+        self.tracked.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn record(&self, owner: &str, repo: &str, snapshot: Snapshot, now: u64) {
+        self.snapshots
+            .lock()
+            .unwrap()
+            .entry((owner.to_string(), repo.to_string()))
+            .or_default()
+            .push_back((now, snapshot));
+    }
+
+    /// Drops snapshots older than `retention_secs` to bound memory use.
+    pub fn prune(&self, retention_secs: u64, now: u64) {
+        let cutoff = now.saturating_sub(retention_secs);
+        let mut snapshots = self.snapshots.lock().unwrap();
+        for series in snapshots.values_mut() {
+            while matches!(series.front(), Some((ts, _)) if *ts < cutoff) {
+                series.pop_front();
+            }
+        }
+    }
+
+    /// Growth over the last `window_secs`, comparing the newest snapshot to
+    /// the oldest one still inside the window.
+    pub fn deltas(&self, owner: &str, repo: &str, window_secs: u64, now: u64) -> Deltas {
+        let snapshots = self.snapshots.lock().unwrap();
+        let Some(series) = snapshots.get(&(owner.to_string(), repo.to_string())) else {
+            return Deltas::default();
+        };
+
+        let cutoff = now.saturating_sub(window_secs);
+        let oldest_in_window = series.iter().find(|(ts, _)| *ts >= cutoff).or(series.back());
+
+        match (oldest_in_window, series.back()) {
+            (Some((_, oldest)), Some((_, newest))) => Deltas {
+                stars: newest.stargazers_count as i64 - oldest.stargazers_count as i64,
+                forks: newest.forks_count as i64 - oldest.forks_count as i64,
+                watchers: newest.watchers_count as i64 - oldest.watchers_count as i64,
+            },
+            _ => Deltas::default(),
+        }
+    }
+
+    /// Star counts within the last `window_secs`, oldest first, for a small
+    /// sparkline. Empty when there's no history yet.
+    pub fn stars_sparkline(&self, owner: &str, repo: &str, window_secs: u64, now: u64) -> Vec<u32> {
+        let snapshots = self.snapshots.lock().unwrap();
+        let Some(series) = snapshots.get(&(owner.to_string(), repo.to_string())) else {
+            return Vec::new();
+        };
+
+        let cutoff = now.saturating_sub(window_secs);
+        series
+            .iter()
+            .filter(|(ts, _)| *ts >= cutoff)
+            .map(|(_, snapshot)| snapshot.stargazers_count)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(stars: u32) -> Snapshot {
+        Snapshot {
+            stargazers_count: stars,
+            forks_count: stars / 2,
+            watchers_count: stars,
+        }
+    }
+
+    #[test]
+    fn deltas_with_no_history_are_zero() {
+        let store = HistoryStore::new();
+        let deltas = store.deltas("owner", "repo", HOUR, 1_000);
+        assert_eq!(deltas.stars, 0);
+        assert_eq!(deltas.forks, 0);
+        assert_eq!(deltas.watchers, 0);
+        assert!(store.stars_sparkline("owner", "repo", HOUR, 1_000).is_empty());
+    }
+
+    #[test]
+    fn deltas_with_a_single_snapshot_are_zero() {
+        let store = HistoryStore::new();
+        store.record("owner", "repo", snapshot(100), 1_000);
+
+        let deltas = store.deltas("owner", "repo", HOUR, 1_000);
+        assert_eq!(deltas.stars, 0);
+    }
+
+    #[test]
+    fn deltas_ignore_a_single_snapshot_older_than_the_window() {
+        let store = HistoryStore::new();
+        store.record("owner", "repo", snapshot(100), 0);
+
+        // The only snapshot on record is far outside a 1-hour window.
+        let deltas = store.deltas("owner", "repo", HOUR, 10 * HOUR);
+        assert_eq!(deltas.stars, 0);
+    }
+
+    #[test]
+    fn deltas_compare_newest_against_oldest_snapshot_inside_the_window() {
+        let store = HistoryStore::new();
+        store.record("owner", "repo", snapshot(100), 0);
+        store.record("owner", "repo", snapshot(150), 50);
+        store.record("owner", "repo", snapshot(200), 100);
+
+        // window=60, now=100 -> cutoff=40, so the t=0 snapshot falls outside
+        // the window and the t=50 snapshot becomes "oldest".
+        let deltas = store.deltas("owner", "repo", 60, 100);
+        assert_eq!(deltas.stars, 50);
+        assert_eq!(deltas.forks, 25);
+    }
+
+    #[test]
+    fn sparkline_only_includes_snapshots_inside_the_window() {
+        let store = HistoryStore::new();
+        store.record("owner", "repo", snapshot(100), 0);
+        store.record("owner", "repo", snapshot(150), 50);
+        store.record("owner", "repo", snapshot(200), 100);
+
+        let sparkline = store.stars_sparkline("owner", "repo", 60, 100);
+        assert_eq!(sparkline, vec![150, 200]);
+    }
+
+    #[test]
+    fn prune_drops_snapshots_older_than_retention() {
+        let store = HistoryStore::new();
+        store.record("owner", "repo", snapshot(100), 0);
+        store.record("owner", "repo", snapshot(200), 100);
+
+        store.prune(50, 100);
+
+        // Only the t=100 snapshot survives, so there's nothing left to diff
+        // against within any window.
+        let deltas = store.deltas("owner", "repo", DAY, 100);
+        assert_eq!(deltas.stars, 0);
+        assert_eq!(store.stars_sparkline("owner", "repo", DAY, 100), vec![200]);
+    }
+}
@@ -1,21 +1,73 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::Arc,
+};
 
 use askama::Template;
 use axum::{
-    extract::Path,
-    http::StatusCode,
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{Html, IntoResponse, Response},
     routing::get,
     Router,
 };
-use octocrab::models::Repository;
+use octocrab::{models::Repository, Octocrab};
 use reqwest::{Client, Url};
 use serde::{Deserialize, Serialize};
+use shuttle_runtime::SecretStore;
 use tower_http::services::ServeDir;
 
+mod cache;
+mod config;
+mod error;
+mod history;
+mod metrics;
 mod utils;
 
-use utils::language::{get_language_color, get_language_size};
+use cache::EtagCache;
+use config::AppConfig;
+use error::AppError;
+use history::HistoryStore;
+use utils::language::{get_language_color, get_language_size, load_language_colors};
+
+#[derive(Clone)]
+struct AppState {
+    http_client: Client,
+    etag_cache: Arc<EtagCache>,
+    /// Authenticated octocrab client used by the background history poller
+    /// (see `spawn_history_poller`). `get_repository`/`get_repository_inner`
+    /// don't go through this client: they need raw `If-None-Match` support
+    /// for conditional requests, which octocrab doesn't expose, so they hit
+    /// GitHub via `http_client`/`etag_cache` with `github_token` below sent
+    /// as a bearer token instead.
+    octocrab: Arc<Octocrab>,
+    github_token: Option<String>,
+    history: Arc<HistoryStore>,
+    /// Language-to-color lookup loaded once at startup from `colors.json`.
+    language_colors: Arc<HashMap<String, String>>,
+}
+
+fn cache_max_age_secs() -> u64 {
+    std::env::var("CACHE_MAX_AGE_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(60)
+}
+
+fn history_poll_interval_secs() -> u64 {
+    std::env::var("HISTORY_POLL_INTERVAL_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(5 * 60)
+}
+
+fn history_retention_secs() -> u64 {
+    std::env::var("HISTORY_RETENTION_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(7 * history::DAY)
+}
 
 #[derive(Debug, Deserialize)]
 struct RepoRequest {
@@ -44,9 +96,9 @@ struct HelloTemplate<'a> {
 
 #[derive(Template)]
 #[template(path = "error.html")]
-struct ErrorTemplate {
-    code: StatusCode,
-    message: String,
+pub(crate) struct ErrorTemplate {
+    pub(crate) code: StatusCode,
+    pub(crate) message: String,
 }
 
 #[derive(Template)]
@@ -61,6 +113,10 @@ struct RepoTemplate {
     watchers_count: u32,
     forks_count: u32,
     languages: Vec<Language>,
+    stars_delta_1h: i64,
+    stars_delta_24h: i64,
+    forks_delta_24h: i64,
+    stars_sparkline: Vec<u32>,
 }
 
 struct HtmlTemplate<T>(T);
@@ -90,36 +146,25 @@ async fn hello_from_the_server() -> &'static str {
     "Hello!"
 }
 
-async fn get_repository_languages(url: Url) -> Result<Vec<Language>, (StatusCode, String)> {
-    let response = Client::new()
-        .get(url)
-        .header("User-Agent", "repos-toolbox-api")
-        .send()
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    if !response.status().is_success() {
-        let error_message = format!(
-            "Error fetching language data. Status code: {}",
-            response.status()
-        );
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, error_message));
-    }
+async fn get_repository_languages(
+    state: &AppState,
+    owner: &str,
+    repo: &str,
+    url: Url,
+) -> Result<Vec<Language>, AppError> {
+    let key = format!("languages:{owner}/{repo}");
+    let body = state
+        .etag_cache
+        .fetch(&state.http_client, &key, url, state.github_token.as_deref())
+        .await?;
 
-    let response_text = response
-        .text()
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let languages_response: HashMap<String, u64> = serde_json::from_str(&response_text)
-        // .map(|lang: u64| )
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let languages_response: HashMap<String, u64> = serde_json::from_str(&body)?;
     let languages_total = languages_response.values().sum::<u64>();
     let mut languages: Vec<Language> = Vec::new();
 
     for (name, value) in languages_response {
         let size = get_language_size(&value, &languages_total);
-        let color = get_language_color(&name).trim_matches('"').to_string();
+        let color = get_language_color(&state.language_colors, &name);
         let language = Language { name, size, color };
         languages.push(language);
     }
@@ -127,19 +172,43 @@ async fn get_repository_languages(url: Url) -> Result<Vec<Language>, (StatusCode
     Ok(languages)
 }
 
-async fn get_repository(
-    Path((owner, repo)): Path<(String, String)>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let response = octocrab::instance()
-        .repos(owner, repo)
-        .get()
+async fn get_repository_inner(
+    state: &AppState,
+    owner: String,
+    repo: String,
+) -> Result<RepoTemplate, AppError> {
+    let repo_url = Url::parse(&format!("https://api.github.com/repos/{owner}/{repo}"))
+        .expect("owner/repo produce a valid GitHub API URL");
+    let key = format!("repo:{owner}/{repo}");
+    let body = state
+        .etag_cache
+        .fetch(
+            &state.http_client,
+            &key,
+            repo_url,
+            state.github_token.as_deref(),
+        )
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|err| match err {
+            AppError::Upstream(status, _) if status == StatusCode::NOT_FOUND => {
+                AppError::RepoNotFound
+            }
+            err => err,
+        })?;
+
+    let repo: Repository = serde_json::from_str(&body)?;
+    let url = repo.clone().languages_url.unwrap();
+    let languages: Vec<Language> = get_repository_languages(state, &owner, &repo.name, url).await?;
+
+    state.history.track(&owner, &repo.name);
+    let now = history::now_secs();
+    let stars_1h = state.history.deltas(&owner, &repo.name, history::HOUR, now);
+    let stars_24h = state.history.deltas(&owner, &repo.name, history::DAY, now);
+    let stars_sparkline = state
+        .history
+        .stars_sparkline(&owner, &repo.name, history::DAY, now);
 
-    let repo = response.clone();
-    let url = response.clone().languages_url.unwrap();
-    let languages: Vec<Language> = get_repository_languages(url).await?;
-    let template = RepoTemplate {
+    Ok(RepoTemplate {
         name: repo.name,
         owner: match repo.owner.clone() {
             Some(owner) => owner.login,
@@ -158,19 +227,126 @@ async fn get_repository(
         watchers_count: repo.watchers_count.unwrap_or(0),
         forks_count: repo.forks_count.unwrap_or(0),
         languages,
+        stars_delta_1h: stars_1h.stars,
+        stars_delta_24h: stars_24h.stars,
+        forks_delta_24h: stars_24h.forks,
+        stars_sparkline,
+    })
+}
+
+fn weak_etag(body: &str) -> String {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    body.hash(&mut hasher);
+    format!("W/\"{:x}\"", hasher.finish())
+}
+
+fn matches_etag(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value == etag)
+        .unwrap_or(false)
+}
+
+async fn get_repository(
+    Path((owner, repo)): Path<(String, String)>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Response {
+    let template = match get_repository_inner(&state, owner, repo).await {
+        Ok(template) => template,
+        Err(err) => return err.into_response_with_headers(&headers),
     };
 
-    Ok(HtmlTemplate(template))
+    let html = match template.render() {
+        Ok(html) => html,
+        Err(err) => return AppError::from(err).into_response_with_headers(&headers),
+    };
+
+    let etag = weak_etag(&html);
+    let etag_header = HeaderValue::from_str(&etag).expect("hashed etag is valid header value");
+    let cache_control_header = HeaderValue::from_str(&format!(
+        "public, max-age={}, stale-if-error=60",
+        cache_max_age_secs()
+    ))
+    .expect("cache-control value is a valid header value");
+
+    let mut response = if matches_etag(&headers, &etag) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        Html(html).into_response()
+    };
+    response.headers_mut().insert(header::ETAG, etag_header);
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, cache_control_header);
+    response
+}
+
+fn spawn_history_poller(octocrab: Arc<Octocrab>, history: Arc<HistoryStore>) {
+    let interval = std::time::Duration::from_secs(history_poll_interval_secs());
+    let retention = history_retention_secs();
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let now = history::now_secs();
+
+            for (owner, repo) in history.tracked_repos() {
+                match octocrab.repos(&owner, &repo).get().await {
+                    Ok(details) => history.record(
+                        &owner,
+                        &repo,
+                        history::Snapshot {
+                            stargazers_count: details.stargazers_count.unwrap_or(0),
+                            forks_count: details.forks_count.unwrap_or(0),
+                            watchers_count: details.watchers_count.unwrap_or(0),
+                        },
+                        now,
+                    ),
+                    Err(err) => {
+                        eprintln!("history poll failed for {owner}/{repo}: {err}");
+                    }
+                }
+            }
+
+            history.prune(retention, now);
+        }
+    });
 }
 
 #[shuttle_runtime::main]
-async fn axum() -> shuttle_axum::ShuttleAxum {
+async fn axum(#[shuttle_runtime::Secrets] secrets: SecretStore) -> shuttle_axum::ShuttleAxum {
+    let config = AppConfig::from_secrets(&secrets);
+    let octocrab = config::build_octocrab(&config)
+        .expect("GITHUB_TOKEN, if set, should produce a valid octocrab client");
+
+    let octocrab = Arc::new(octocrab);
+    let state = AppState {
+        http_client: Client::new(),
+        etag_cache: Arc::new(EtagCache::new()),
+        octocrab: octocrab.clone(),
+        github_token: config.github_token,
+        history: Arc::new(HistoryStore::new()),
+        language_colors: Arc::new(load_language_colors(std::path::Path::new("colors.json"))),
+    };
+
+    spawn_history_poller(octocrab, state.history.clone());
+
+    let metrics_handle = metrics::install_recorder();
+
     let api_router = Router::new()
         .route("/hello", get(hello_from_the_server))
-        .route("/repo/:owner/:repo", get(get_repository));
+        .route("/repo/:owner/:repo", get(get_repository))
+        .layer(axum::middleware::from_fn(metrics::track_metrics))
+        .with_state(state);
     let router = Router::new()
         .nest("/api", api_router)
         .route("/", get(hello_world))
+        .route("/health", get(metrics::health))
+        .route("/metrics", get(move || async move { metrics_handle.render() }))
         .nest_service("/assets", ServeDir::new(PathBuf::from("assets")));
 
     Ok(router.into())
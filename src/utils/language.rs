@@ -1,21 +1,16 @@
-use std::{
-    fs::{self},
-    path::Path,
-};
+use std::{collections::HashMap, fs, path::Path};
 
-use serde_json::{from_str, json};
-
-pub fn get_language_color(language: &str) -> String {
-    let file = fs::read_to_string(Path::new("colors.json")).expect("Failed to read colors.json");
-    let colors = from_str::<serde_json::Value>(&file);
+// Runs once at startup and panics on a missing/malformed file, instead of
+// re-reading it on every request and silently returning empty colors.
+pub fn load_language_colors(path: &Path) -> HashMap<String, String> {
+    let file = fs::read_to_string(path)
+        .unwrap_or_else(|err| panic!("failed to read {}: {err}", path.display()));
+    serde_json::from_str(&file)
+        .unwrap_or_else(|err| panic!("failed to parse {}: {err}", path.display()))
+}
 
-    match colors {
-        Ok(colors) => match colors.get(language) {
-            Some(color) => color.to_string(),
-            None => json!("").to_string(),
-        },
-        Err(_) => json!("").to_string(),
-    }
+pub fn get_language_color(colors: &HashMap<String, String>, language: &str) -> String {
+    colors.get(language).cloned().unwrap_or_default()
 }
 
 pub fn get_language_size(language: &u64, total: &u64) -> f64 {